@@ -1,5 +1,6 @@
 use indicatif::{ProgressBar, ProgressState, ProgressStyle};
 use md5::{Digest, Md5};
+use sha2::Sha256;
 use std::io::{Read, Write};
 use std::{
     env, fs,
@@ -10,6 +11,15 @@ use std::{
 use which::which;
 
 const VERSION: &str = "1.11.1.1155";
+// No pinned VERSION_SHA256 here: offline/air-gapped checksum pinning is out
+// of scope for now (it needs a real digest sourced from a verified
+// download, not a guessed one). See ensure_install's checksum check.
+
+/// clojure-tools version to install/run, allowing `CLJ_TOOLS_VERSION` to
+/// override the compiled-in default without recompiling.
+fn tools_version() -> String {
+    env::var("CLJ_TOOLS_VERSION").unwrap_or_else(|_| VERSION.to_owned())
+}
 
 #[derive(Debug)]
 pub enum ExecOpts {
@@ -45,26 +55,33 @@ pub struct CljOpts {
     /// remain clojure args
     clojure_args: Vec<String>,
     trace: bool,
+    /// -Sforce        Force classpath recomputation, ignoring any cache
+    force: bool,
+    /// -Srepro        Ignore the user-level deps.edn config
+    repro: bool,
+    /// -Sdescribe     Print an EDN map describing the resolved runtime
+    describe: bool,
 }
 
 // return install directory
 fn ensure_install() -> anyhow::Result<PathBuf> {
     // r#"C:\Windows\system32\WindowsPowerShell\v1.0\Modules\ClojureTools\"#
+    let version = tools_version();
     let install_dir = get_clj_config()?.join("clojure-tools");
     let version_file = install_dir.join("VERSION");
 
     if version_file.exists() {
-        let version = fs::read_to_string(&version_file)?;
-        if version == VERSION {
+        let installed_version = fs::read_to_string(&version_file)?;
+        if installed_version == version {
             return Ok(install_dir.join("ClojureTools"));
         }
     }
 
-    println!("Installing clojure-tools {}...", VERSION);
+    println!("Installing clojure-tools {}...", version);
     // Let's download it!
     let tools_url = format!(
         "https://download.clojure.org/install/clojure-tools-{}.zip",
-        VERSION
+        version
     );
 
     fs::create_dir_all(&install_dir)?;
@@ -87,12 +104,14 @@ fn ensure_install() -> anyhow::Result<PathBuf> {
         .progress_chars("#>-"));
 
     let mut nwritten = 0;
+    let mut hasher = Sha256::new();
 
     while let Ok(n) = resp.read(&mut buf) {
         if n == 0 {
             break;
         }
         tmpfile.write_all(&buf[..n])?;
+        hasher.update(&buf[..n]);
         pb.inc(n as _);
         nwritten += n;
     }
@@ -104,10 +123,35 @@ fn ensure_install() -> anyhow::Result<PathBuf> {
         anyhow::bail!("download fail");
     }
 
+    // No offline fallback digest (out of scope for now, see VERSION); an
+    // unfetchable `.sha256` skips verification the same way for every version.
+    let computed_digest = format!("{:x}", hasher.finalize());
+    let expected_digest = reqwest::blocking::get(format!("{}.sha256", tools_url))
+        .ok()
+        .filter(|r| r.status().is_success())
+        .and_then(|r| r.text().ok())
+        .and_then(|s| s.split_whitespace().next().map(str::to_owned));
+
+    if let Some(expected_digest) = expected_digest {
+        if computed_digest != expected_digest {
+            anyhow::bail!(
+                "checksum mismatch for clojure-tools-{}.zip: expected {}, got {}",
+                version,
+                expected_digest,
+                computed_digest
+            );
+        }
+    } else {
+        println!(
+            "Couldn't fetch checksum for clojure-tools-{}.zip, skipping verification",
+            version
+        );
+    }
+
     let mut zipfile = zip::ZipArchive::new(tmpfile).unwrap();
     zipfile.extract(&install_dir)?;
 
-    fs::write(version_file, VERSION)?;
+    fs::write(version_file, version)?;
 
     Ok(install_dir.join("ClojureTools"))
 }
@@ -126,9 +170,83 @@ fn get_java_command() -> anyhow::Result<PathBuf> {
             }
         }
     }
+    #[cfg(windows)]
+    if let Some(java) = find_java_in_registry() {
+        return Ok(java);
+    }
+
     anyhow::bail!("Couldn't find 'java'. Please set JAVA_HOME.")
 }
 
+/// Look up an installed JDK in the Windows registry.
+#[cfg(windows)]
+fn find_java_in_registry() -> Option<PathBuf> {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
+
+    for subkey_name in &[
+        "SOFTWARE\\JavaSoft\\JDK",
+        "SOFTWARE\\JavaSoft\\Java Development Kit",
+        "SOFTWARE\\JavaSoft\\Java Runtime Environment",
+    ] {
+        let Ok(javasoft) = hklm.open_subkey(subkey_name) else {
+            continue;
+        };
+        let Ok(current_version) = javasoft.get_value::<String, _>("CurrentVersion") else {
+            continue;
+        };
+        let Ok(version_key) = javasoft.open_subkey(&current_version) else {
+            continue;
+        };
+        let Ok(java_home) = version_key.get_value::<String, _>("JavaHome") else {
+            continue;
+        };
+        let java = PathBuf::from(java_home).join("bin").join("java.exe");
+        if java.exists() {
+            if let Ok(java) = dunce::canonicalize(java) {
+                return Some(java);
+            }
+        }
+    }
+
+    None
+}
+
+/// Are we running under Cygwin/MSYS (e.g. Git-Bash) on Windows?
+fn is_msys_shell() -> bool {
+    cfg!(windows)
+        && (env::var("MSYSTEM").is_ok()
+            || env::var("OSTYPE")
+                .map(|v| v.contains("msys") || v.contains("cygwin"))
+                .unwrap_or(false))
+}
+
+/// Convert a POSIX-style path to its Windows-native equivalent via `cygpath -w`.
+fn to_windows_path(path: &str) -> String {
+    Command::new("cygpath")
+        .arg("-w")
+        .arg(path)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_owned())
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// Render `p` for passing to a java `Command`, translating through `cygpath`
+/// first when running under Cygwin/MSYS.
+fn cmd_path(p: &Path, msys: bool) -> String {
+    let s = p.display().to_string();
+    if msys {
+        to_windows_path(&s)
+    } else {
+        s
+    }
+}
+
 /// Determine user config directory
 fn get_clj_config() -> anyhow::Result<PathBuf> {
     env::var("CLJ_CONFIG")
@@ -146,7 +264,7 @@ fn get_clj_cache() -> anyhow::Result<PathBuf> {
         .or_else(|_| get_clj_config().map(|s| s.join(".cpcache")))
 }
 
-fn parse_args() -> Option<(ExecOpts, CljOpts)> {
+fn parse_args() -> anyhow::Result<Option<(ExecOpts, CljOpts)>> {
     let args = env::args().collect::<Vec<_>>();
 
     // println!("args => {:?}", args);
@@ -157,8 +275,8 @@ fn parse_args() -> Option<(ExecOpts, CljOpts)> {
 
     while let Some(arg) = it.next() {
         if arg == "-version" || arg == "--version" {
-            println!("Clojure CLI version {}", VERSION);
-            return None;
+            println!("Clojure CLI version {}", tools_version());
+            return Ok(None);
         } else if arg.starts_with("-J") {
             if clj_opts.jvm_opts.is_empty() {
                 clj_opts.jvm_opts = arg[2..].to_owned();
@@ -193,6 +311,12 @@ fn parse_args() -> Option<(ExecOpts, CljOpts)> {
         } else if arg.starts_with("-A") {
             // repl alias
             clj_opts.repl_aliases.push(arg[2..].to_owned());
+        } else if arg.starts_with("-R") || arg.starts_with("-C") {
+            // deprecated resolve-deps/classpath aliases from older launchers
+            eprintln!("-R/-C is deprecated, use -A/-M/-X");
+            clj_opts.repl_aliases.push(arg[2..].to_owned());
+        } else if arg.starts_with("-O") {
+            anyhow::bail!("-O is no longer supported");
         } else if arg == "-X" {
             exec_opts = ExecOpts::Exec("".to_owned());
             clj_opts.clojure_args.extend(it);
@@ -211,6 +335,12 @@ fn parse_args() -> Option<(ExecOpts, CljOpts)> {
             unimplemented!()
         } else if arg == "-Spath" {
             clj_opts.path = true;
+        } else if arg == "-Sforce" {
+            clj_opts.force = true;
+        } else if arg == "-Srepro" {
+            clj_opts.repro = true;
+        } else if arg == "-Sdescribe" {
+            clj_opts.describe = true;
         } else if arg == "-Strace" {
             clj_opts.trace = true;
         } else if arg == "-Sverbose" {
@@ -239,7 +369,41 @@ fn parse_args() -> Option<(ExecOpts, CljOpts)> {
         }
     }
 
-    Some((exec_opts, clj_opts))
+    Ok(Some((exec_opts, clj_opts)))
+}
+
+/// Is the cached classpath at `cp_file` stale relative to `config_paths` and the manifest it was built from?
+fn classpath_is_stale(
+    cp_file: &Path,
+    config_paths: &[PathBuf],
+    manifest_file: &Path,
+    force: bool,
+) -> anyhow::Result<bool> {
+    if force || !cp_file.exists() {
+        return Ok(true);
+    }
+    let cp_modified = cp_file.metadata()?.modified()?;
+
+    for config_path in config_paths {
+        if config_path.exists() && config_path.metadata()?.modified()? > cp_modified {
+            return Ok(true);
+        }
+    }
+
+    if manifest_file.exists() {
+        for line in fs::read_to_string(manifest_file)?.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let manifest_path = Path::new(line);
+            if manifest_path.exists() && manifest_path.metadata()?.modified()? > cp_modified {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
 }
 
 fn md5_string(s: &str) -> String {
@@ -249,11 +413,12 @@ fn md5_string(s: &str) -> String {
 }
 
 fn main() -> anyhow::Result<()> {
-    let (exec_opts, clj_opts) = match parse_args() {
+    let (exec_opts, clj_opts) = match parse_args()? {
         Some(v) => v,
         None => return Ok(()),
     };
 
+    let version = tools_version();
     let install_dir = ensure_install()?;
 
     let java = get_java_command()?;
@@ -293,13 +458,17 @@ fn main() -> anyhow::Result<()> {
 
     // Chain deps.edn in config paths. repro=skip config dir
     let project_config = "deps.edn";
-    // TODO: handle Repro options
-    let user_config = config_dir.join("deps.end");
-    let config_paths = &[
-        install_dir.join("deps.edn"),
-        config_dir.join("deps.edn"),
-        "deps.edn".into(),
-    ];
+    let user_config = config_dir.join("deps.edn");
+    let config_paths: Vec<PathBuf> = if clj_opts.repro {
+        vec![install_dir.join("deps.edn"), "deps.edn".into()]
+    } else {
+        vec![
+            install_dir.join("deps.edn"),
+            config_dir.join("deps.edn"),
+            "deps.edn".into(),
+        ]
+    };
+    let config_paths = config_paths.as_slice();
 
     // Determine whether to use user or project cache
     let cache_dir = if Path::new("deps.edn").exists() {
@@ -308,6 +477,24 @@ fn main() -> anyhow::Result<()> {
         user_cache_dir.clone()
     };
 
+    if clj_opts.describe {
+        let config_files = config_paths
+            .iter()
+            .filter(|p| p.exists())
+            .map(|p| format!("\"{}\"", p.display()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!("{{:version \"{}\"", version);
+        println!(" :config-files [{}]", config_files);
+        println!(" :config-user \"{}\"", user_config.display());
+        println!(" :config-project \"{}\"", project_config);
+        println!(" :install-dir \"{}\"", install_dir.display());
+        println!(" :config-dir \"{}\"", config_dir.display());
+        println!(" :cache-dir \"{}\"", cache_dir.display());
+        println!(" :clj-version \"{}\"}}", version);
+        return Ok(());
+    }
+
     // Construct location of cached classpath file
     let cache_key = format!(
         "|{:?}|{:?}|{:?}|{:?}|",
@@ -325,8 +512,13 @@ fn main() -> anyhow::Result<()> {
     let basis_file = cache_dir.join(cache_key_hash.to_owned() + ".basis");
     let manifest_file = cache_dir.join(cache_key_hash.to_owned() + ".manifest");
 
+    // Cygwin/MSYS shells (e.g. Git-Bash) hand us POSIX-style paths, but every
+    // java Command below (make-classpath2 and the final exec) needs
+    // Windows-native ones.
+    let msys = is_msys_shell();
+
     if clj_opts.verbose {
-        println!("version      {}", VERSION);
+        println!("version      {}", version);
         println!("install_dir  {}", install_dir.display());
         println!("config_dir   {}", config_dir.display());
         println!("config_paths {:?}", config_paths);
@@ -373,46 +565,55 @@ fn main() -> anyhow::Result<()> {
         tools_args.push("--trace".into());
     }
 
-    // If stale, run make-classpath to refresh cached classpath
-    if clj_opts.verbose {
-        println!("Refreshing classpath");
-        println!("D tools args: {:?}", tools_args);
-    }
-    let tools_cp = install_dir.join(format!("clojure-tools-{}.jar", VERSION));
+    // If stale, run make-classpath to refresh cached classpath. Otherwise
+    // skip the JVM spawn entirely and read the cached files directly.
+    // -Stree/-Strace only take effect as make-classpath2 side-effects,
+    // so they need a refresh even when the cached classpath itself is fresh.
+    // -Spom forces a refresh as well; no pom option is passed to make-classpath2.
+    let force_refresh = clj_opts.force || clj_opts.tree || clj_opts.trace || clj_opts.pom;
+    if classpath_is_stale(&cp_file, config_paths, &manifest_file, force_refresh)? {
+        if clj_opts.verbose {
+            println!("Refreshing classpath");
+            println!("D tools args: {:?}", tools_args);
+        }
+        let tools_cp = cmd_path(&install_dir.join(format!("clojure-tools-{}.jar", version)), msys);
 
-    let child = Command::new(&java)
-        .arg("-classpath")
-        .arg(tools_cp)
-        .args([
+        let mut cmd = Command::new(&java);
+        cmd.arg("-classpath").arg(tools_cp).args([
             "clojure.main",
             "-m",
             "clojure.tools.deps.alpha.script.make-classpath2",
-        ])
-        .arg("--config-user")
-        .arg(user_config.as_os_str())
-        .arg("--config-project")
-        .arg(project_config)
-        .arg("--basis-file")
-        .arg(basis_file.as_os_str())
-        .arg("--libs-file")
-        .arg(libs_file.as_os_str())
-        .arg("--cp-file")
-        .arg(cp_file.as_os_str())
-        .arg("--jvm-file")
-        .arg(jvm_file.as_os_str())
-        .arg("--main-file")
-        .arg(main_file.as_os_str())
-        .arg("--manifest-file")
-        .arg(manifest_file.as_os_str())
-        .args(tools_args)
-        .spawn()
-        .expect("run");
-    let output = child.wait_with_output()?;
-    if !output.status.success() {
-        anyhow::bail!("refresh classpath: {}", output.status);
+        ]);
+        if !clj_opts.repro {
+            cmd.arg("--config-user").arg(cmd_path(&user_config, msys));
+        }
+        let child = cmd
+            .arg("--config-project")
+            .arg(project_config)
+            .arg("--basis-file")
+            .arg(cmd_path(&basis_file, msys))
+            .arg("--libs-file")
+            .arg(cmd_path(&libs_file, msys))
+            .arg("--cp-file")
+            .arg(cmd_path(&cp_file, msys))
+            .arg("--jvm-file")
+            .arg(cmd_path(&jvm_file, msys))
+            .arg("--main-file")
+            .arg(cmd_path(&main_file, msys))
+            .arg("--manifest-file")
+            .arg(cmd_path(&manifest_file, msys))
+            .args(tools_args)
+            .spawn()
+            .expect("run");
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            anyhow::bail!("refresh classpath: {}", output.status);
+        }
+    } else if clj_opts.verbose {
+        println!("D: cached classpath is fresh, skipping make-classpath2");
     }
 
-    let cp = fs::read_to_string(cp_file)?;
+    let cp = fs::read_to_string(&cp_file)?;
     if clj_opts.verbose {
         println!("D class path: {}", cp);
         println!("D clojure args: {:?}", clj_opts.clojure_args);
@@ -424,17 +625,25 @@ fn main() -> anyhow::Result<()> {
         Default::default()
     };
 
+    let basis_path = cmd_path(&basis_file, msys);
+    let cp = if msys {
+        cp.split(';').map(to_windows_path).collect::<Vec<_>>().join(";")
+    } else {
+        cp
+    };
+
     let maybe_child = match exec_opts {
         ExecOpts::Exec(_) | ExecOpts::Tool(_) => {
+            let exec_jar = cmd_path(&install_dir.join("exec.jar"), msys);
             let cp = if cfg!(windows) {
-                format!("{};{}", cp, install_dir.join("exec.jar").display())
+                format!("{};{}", cp, exec_jar)
             } else {
-                format!("{}:{}", cp, install_dir.join("exec.jar").display())
+                format!("{}:{}", cp, exec_jar)
             };
             Command::new(&java)
                 .args(jvm_cache_opts.split_whitespace().collect::<Vec<_>>())
                 .args(clj_opts.jvm_opts.split_whitespace().collect::<Vec<_>>())
-                .arg(format!("-Dclojure.basis={}", basis_file.display()))
+                .arg(format!("-Dclojure.basis={}", basis_path))
                 .arg("-classpath")
                 .arg(cp)
                 .arg("clojure.main")
@@ -452,7 +661,7 @@ fn main() -> anyhow::Result<()> {
             Command::new(&java)
                 .args(jvm_cache_opts.split_whitespace().collect::<Vec<_>>())
                 .args(clj_opts.jvm_opts.split_whitespace().collect::<Vec<_>>())
-                .arg(format!("-Dclojure.basis={}", basis_file.display()))
+                .arg(format!("-Dclojure.basis={}", basis_path))
                 .arg("-classpath")
                 .arg(cp)
                 .arg("clojure.main")